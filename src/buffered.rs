@@ -0,0 +1,224 @@
+//! A UDP sink that packs multiple datagrams into a single packet.
+//!
+//! `UdpClient::send` issues one `send_to` per metric, which is wasteful for
+//! applications emitting thousands of points per second. `BufferedUdpClient`
+//! instead accumulates formatted datagrams in memory and flushes them as a
+//! single payload once it's about to exceed a configurable size.
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_std::net::UdpSocket;
+use async_std::sync::Mutex;
+use async_std::task;
+
+use crate::{Config, DatagramFormat, Metric, Pcg32, Result};
+
+/// Default max UDP payload size in bytes, chosen to stay under the typical
+/// Ethernet MTU (1500 bytes) once IP/UDP headers are accounted for.
+pub const DEFAULT_MAX_PAYLOAD_SIZE: usize = 1432;
+
+struct Buffer {
+    bytes: Vec<u8>,
+}
+
+/// `BufferedUdpClient` coalesces many metrics into a single UDP payload
+/// instead of one `send_to` per metric.
+pub struct BufferedUdpClient {
+    socket: UdpSocket,
+    config: Config,
+    max_payload_size: usize,
+    buffer: Mutex<Buffer>,
+    rng: Pcg32,
+}
+
+impl BufferedUdpClient {
+    pub async fn new() -> Result<Self> {
+        Self::with_config(Config::default()).await
+    }
+
+    /// Construct a client with a specific Config.
+    pub async fn with_config(config: Config) -> Result<Self> {
+        config.validate_from_addr()?;
+        config.validate_constant_tags()?;
+        Ok(Self {
+            socket: UdpSocket::bind(config.from_addr.clone()).await?,
+            config,
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+            buffer: Mutex::new(Buffer { bytes: Vec::new() }),
+            rng: Pcg32::seeded(),
+        })
+    }
+
+    /// Override the default max payload size (~1432 bytes).
+    pub fn max_payload_size(mut self, max_payload_size: usize) -> Self {
+        self.max_payload_size = max_payload_size;
+        self
+    }
+
+    /// Returns `true` if a datagram with the given sample rate should be
+    /// dropped instead of sent.
+    fn should_drop(&self, rate: f64) -> bool {
+        rate < 1.0 && self.rng.next_f64() >= rate
+    }
+
+    /// Spawn a background task that flushes the buffer on a fixed interval,
+    /// so a partially-filled buffer doesn't sit unsent indefinitely between
+    /// bursts of traffic. The task loops forever: awaiting the returned
+    /// handle never completes, and dropping it detaches the task rather
+    /// than stopping it; `JoinHandle::cancel` stops it.
+    pub fn spawn_flush_interval(self: &Arc<Self>, interval: Duration) -> task::JoinHandle<()> {
+        let this = Arc::clone(self);
+        task::spawn(async move {
+            loop {
+                task::sleep(interval).await;
+                let _ = this.flush().await;
+            }
+        })
+    }
+
+    /// Format `df` and append it to the buffer, flushing the current
+    /// contents first if the new datagram wouldn't fit. A single datagram
+    /// that wouldn't fit in an empty buffer (accounting for its trailing
+    /// separator) bypasses the buffer entirely and is sent on its own.
+    pub async fn send<M>(&self, df: &M) -> Result<()>
+    where
+        M: DatagramFormat,
+    {
+        df.validate()?;
+        if self.should_drop(df.sample_rate()) {
+            return Ok(());
+        }
+        let mut tags = self.config.constant_tags.clone();
+        crate::insert_unit_tag(&mut tags, df.unit());
+        let mut datagram = self.config.format_datagram(df);
+        datagram.push_str(&tags.format());
+        // `+ 1` accounts for the trailing separator a buffered datagram
+        // gets below, so a datagram exactly `max_payload_size` bytes long
+        // takes the bypass path instead of overflowing the buffer by one
+        // byte once its separator is appended.
+        let additional = datagram.len() + 1;
+        if additional > self.max_payload_size {
+            self.socket
+                .send_to(datagram.as_bytes(), &self.config.to_addr)
+                .await?;
+            return Ok(());
+        }
+
+        let mut buffer = self.buffer.lock().await;
+        if !buffer.bytes.is_empty() && buffer.bytes.len() + additional > self.max_payload_size {
+            self.flush_locked(&mut buffer).await?;
+        }
+        buffer.bytes.extend_from_slice(datagram.as_bytes());
+        buffer.bytes.push(b'\n');
+        Ok(())
+    }
+
+    /// Flush any buffered datagrams as a single UDP packet.
+    pub async fn flush(&self) -> Result<()> {
+        let mut buffer = self.buffer.lock().await;
+        self.flush_locked(&mut buffer).await
+    }
+
+    async fn flush_locked(&self, buffer: &mut Buffer) -> Result<()> {
+        if buffer.bytes.is_empty() {
+            return Ok(());
+        }
+        self.socket
+            .send_to(&buffer.bytes, &self.config.to_addr)
+            .await?;
+        buffer.bytes.clear();
+        Ok(())
+    }
+
+    /// Await `fut`, emit how long it took as a `Timer` metric named `name`,
+    /// and return its output, so instrumenting an async block is a one-liner.
+    pub async fn time<F, T>(&self, name: &str, fut: F) -> Result<T>
+    where
+        F: std::future::Future<Output = T>,
+    {
+        let start = std::time::Instant::now();
+        let output = fut.await;
+        self.send(&Metric::Timer::<u32>(name, start.elapsed()))
+            .await?;
+        Ok(output)
+    }
+}
+
+impl Drop for BufferedUdpClient {
+    fn drop(&mut self) {
+        // Best-effort: block on the flush so a buffer's worth of metrics
+        // isn't silently lost when the client goes out of scope. Prefer
+        // calling `flush` explicitly before dropping when an async context
+        // is available.
+        let _ = task::block_on(self.flush());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_should_drop_respects_sample_rate() {
+        let client = task::block_on(BufferedUdpClient::new()).unwrap();
+        assert!(client.should_drop(0.0));
+        assert!(!client.should_drop(1.0));
+    }
+
+    #[test]
+    fn test_send_buffers_instead_of_sending_immediately() {
+        task::block_on(async {
+            let client = BufferedUdpClient::new().await.unwrap();
+            client.send(&Metric::Inc::<u32>("custom_metric")).await.unwrap();
+            let buffer = client.buffer.lock().await;
+            assert_eq!(&buffer.bytes, b"custom_metric:1|c\n");
+        });
+    }
+
+    #[test]
+    fn test_flush_sends_and_clears_the_buffer() {
+        task::block_on(async {
+            let client = BufferedUdpClient::new().await.unwrap();
+            client.send(&Metric::Inc::<u32>("custom_metric")).await.unwrap();
+            client.flush().await.unwrap();
+            let buffer = client.buffer.lock().await;
+            assert!(buffer.bytes.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_datagram_exactly_at_max_payload_size_bypasses_buffer() {
+        task::block_on(async {
+            // "custom_metric:1|c" is 17 bytes; with max_payload_size set to
+            // exactly that, buffering it would add a trailing separator and
+            // overflow the budget by one byte, so it must bypass the buffer.
+            let client = BufferedUdpClient::new().await.unwrap().max_payload_size(17);
+            client.send(&Metric::Inc::<u32>("custom_metric")).await.unwrap();
+            let buffer = client.buffer.lock().await;
+            assert!(buffer.bytes.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_datagram_that_leaves_room_for_the_separator_is_buffered() {
+        task::block_on(async {
+            // One more byte of budget than the datagram needs is enough to
+            // also fit its trailing separator, so it should be buffered.
+            let client = BufferedUdpClient::new().await.unwrap().max_payload_size(18);
+            client.send(&Metric::Inc::<u32>("custom_metric")).await.unwrap();
+            let buffer = client.buffer.lock().await;
+            assert_eq!(&buffer.bytes, b"custom_metric:1|c\n");
+        });
+    }
+
+    #[test]
+    fn test_second_send_flushes_buffer_before_it_would_overflow() {
+        task::block_on(async {
+            let client = BufferedUdpClient::new().await.unwrap().max_payload_size(19);
+            client.send(&Metric::Inc::<u32>("custom_metric")).await.unwrap();
+            client.send(&Metric::Inc::<u32>("custom_metric")).await.unwrap();
+            let buffer = client.buffer.lock().await;
+            assert_eq!(&buffer.bytes, b"custom_metric:1|c\n");
+        });
+    }
+}
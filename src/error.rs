@@ -0,0 +1,81 @@
+use std::fmt;
+use std::io;
+use std::net::AddrParseError;
+
+/// Errors that can occur while configuring a client or while sending a
+/// metric or event to DogStatsD.
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying socket operation failed.
+    Io(io::Error),
+    /// A configured address could not be parsed as a socket address.
+    AddrParse(AddrParseError),
+    /// A metric name contained a character reserved by the StatsD wire
+    /// format: `:`, `|`, or `@`.
+    InvalidMetricName(String),
+    /// A tag key or value contained a character reserved by the tag wire
+    /// format: `:`, `|`, or `,`; also returned for an event title or text
+    /// that contains a raw newline.
+    InvalidTag(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::AddrParse(e) => write!(f, "invalid address: {}", e),
+            Error::InvalidMetricName(name) => {
+                write!(f, "metric name {:?} contains a reserved character (':', '|', or '@')", name)
+            }
+            Error::InvalidTag(field) => {
+                write!(f, "{:?} contains a character reserved by the wire format", field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<AddrParseError> for Error {
+    fn from(e: AddrParseError) -> Self {
+        Error::AddrParse(e)
+    }
+}
+
+/// A specialized `Result` for zoomies client operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Rejects metric names containing a character reserved by the StatsD wire
+/// format (`:`, `|`, or `@`), which would otherwise silently corrupt the
+/// emitted datagram.
+pub(crate) fn validate_metric_name(name: &str) -> Result<()> {
+    if name.contains(|c| c == ':' || c == '|' || c == '@') {
+        return Err(Error::InvalidMetricName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Rejects tag keys/values containing a character reserved by the tag wire
+/// format (`:`, `|`, or `,`).
+pub(crate) fn validate_tag(tag: &str) -> Result<()> {
+    if tag.contains(|c| c == ':' || c == '|' || c == ',') {
+        return Err(Error::InvalidTag(tag.to_string()));
+    }
+    Ok(())
+}
+
+/// Rejects event title/text fields containing a raw newline, which would
+/// corrupt the `_e{<TITLE>.length,<TEXT>.length}` header's implied framing.
+/// A literal newline must be escaped (e.g. `\\n`) by the caller instead.
+pub(crate) fn validate_event_field(field: &str) -> Result<()> {
+    if field.contains('\n') {
+        return Err(Error::InvalidTag(field.to_string()));
+    }
+    Ok(())
+}
@@ -8,7 +8,7 @@
 /// _e{21,42}:An exception occurred|Cannot parse JSON request:\\n{"foo: "bar"}|p:low|#err_type:bad_request
 use std::time::SystemTime;
 
-use crate::DatagramFormat;
+use crate::{validate_event_field, DatagramFormat, Result};
 
 #[derive(Clone, PartialEq)]
 pub enum Priority {
@@ -218,6 +218,16 @@ impl DatagramFormat for Event {
         msg.push_str(&at);
         msg
     }
+
+    fn validate(&self) -> Result<()> {
+        validate_event_field(&self.title)?;
+        validate_event_field(&self.text)?;
+        Ok(())
+    }
+
+    fn is_event(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -248,4 +258,18 @@ mod test {
             "_e{7,11}:Chungus|Big Chungus|d:0|h:kevin|k:something_cool|p:low|s:your_app|t:error"
         );
     }
+
+    #[test]
+    fn test_event_validate_rejects_raw_newline() {
+        assert!(Event::new()
+            .title("Chungus")
+            .text("Big\nChungus")
+            .validate()
+            .is_err());
+        assert!(Event::new()
+            .title("Chungus")
+            .text("Big Chungus")
+            .validate()
+            .is_ok());
+    }
 }
@@ -1,9 +1,8 @@
-use zoomies::{Config, Event, Metric, UdpClient};
+use zoomies::{Config, Error, Event, Metric, UdpClient};
 
-use async_std::io;
 use async_std::task;
 
-fn main() -> io::Result<()> {
+fn main() -> Result<(), Error> {
     task::block_on(async {
         let config = Config::new();
         let client = UdpClient::with_config(config).await?;
@@ -5,10 +5,10 @@
 //! Use a `Config` to configure an asynchronous `UdpClient`.
 //!
 //! ```notest
-//! use zoomies::{UdpClient, Config};
+//! use zoomies::{UdpClient, Config, Error};
 //!
 //! #[async_std::main]
-//! async fn main() -> std::io::Result<()> {
+//! async fn main() -> Result<(), Error> {
 //!   let config = Config::new()
 //!                .from_addr("127.0.0.1:10001".into())
 //!                .to_addr("MY_STATSD_HOST:PORT".into())
@@ -18,9 +18,16 @@
 //!   Ok(())
 //! }
 //! ```
-use std::{borrow, collections::HashMap, default, fmt};
+use std::{
+    borrow,
+    collections::HashMap,
+    default, fmt,
+    net::SocketAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
 
-use async_std::{io::Result, net::UdpSocket, os::unix::net::UnixDatagram};
+use async_std::{net::UdpSocket, os::unix::net::UnixDatagram};
 
 mod events;
 pub use events::*;
@@ -28,9 +35,115 @@ pub use events::*;
 mod metrics;
 pub use metrics::*;
 
+mod buffered;
+pub use buffered::*;
+
+mod error;
+pub use error::*;
+
 // Trait that can serialize a type into the DogStatsD datagram format.
 pub trait DatagramFormat {
     fn format(&self) -> String;
+
+    /// The probability, in `[0, 1]`, that this datagram should actually be
+    /// sent over the wire. Defaults to `1.0` (always send); `Sampled`
+    /// overrides this so clients can throttle high-frequency metrics.
+    fn sample_rate(&self) -> f64 {
+        1.0
+    }
+
+    /// Checks that this datagram won't corrupt the DogStatsD wire format
+    /// once formatted, e.g. a metric name or tag containing a reserved
+    /// separator. Defaults to always valid; overridden by types that carry
+    /// user-supplied names, tags, or text.
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// The semantic unit carried by this datagram's value, if any. Since
+    /// DogStatsD has no native unit field, a sending client folds this into
+    /// the emitted tag set as `unit:<label>`. Defaults to none; `WithUnit`
+    /// overrides this.
+    fn unit(&self) -> Option<Unit> {
+        None
+    }
+
+    /// Attach a `Unit` to this metric or event, e.g.
+    /// `Metric::Gauge::<u32>("queue_depth", "42").with_unit(Unit::Count)`.
+    fn with_unit(self, unit: Unit) -> WithUnit<Self>
+    where
+        Self: Sized,
+    {
+        WithUnit(self, unit)
+    }
+
+    /// Whether this datagram is a DogStatsD event rather than a metric.
+    /// Events encode their own `_e{<title_len>,<text_len>}:` framing up
+    /// front, so a sending client must not prefix a configured namespace
+    /// onto the formatted output. Defaults to `false`; `Event` overrides
+    /// this to `true`.
+    fn is_event(&self) -> bool {
+        false
+    }
+}
+
+/// A minimal PCG32 PRNG used to make the client-side sampling decision in
+/// `UdpClient::send`/`UdsClient::send`/`BufferedUdpClient::send`. We roll
+/// our own here instead of pulling in a full RNG crate; a draw is a
+/// single atomic update and never allocates.
+pub(crate) struct Pcg32 {
+    state: AtomicU64,
+}
+
+// Fixed odd increment recommended by the PCG reference implementation.
+const PCG32_MULTIPLIER: u64 = 6364136223846793005;
+const PCG32_INCREMENT: u64 = 1442695040888963407;
+
+impl Pcg32 {
+    /// Seed from the current time so distinct clients don't all draw the
+    /// same sequence.
+    pub(crate) fn seeded() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Pcg32 {
+            state: AtomicU64::new(seed ^ PCG32_INCREMENT),
+        }
+    }
+
+    /// Draw the next value in `[0, 1)`.
+    pub(crate) fn next_f64(&self) -> f64 {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            let new_state = state
+                .wrapping_mul(PCG32_MULTIPLIER)
+                .wrapping_add(PCG32_INCREMENT);
+            match self.state.compare_exchange_weak(
+                state,
+                new_state,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    let xorshifted = (((new_state >> 18) ^ new_state) >> 27) as u32;
+                    let rot = (new_state >> 59) as u32;
+                    let out = xorshifted.rotate_right(rot);
+                    return out as f64 / (u32::MAX as f64 + 1.0);
+                }
+                Err(s) => state = s,
+            }
+        }
+    }
+}
+
+/// Fold a metric's unit, if any, into a tag map as `unit:<label>`, without
+/// overwriting a caller-supplied `unit` tag that's already present.
+fn insert_unit_tag(tags: &mut HashMap<String, String>, unit: Option<Unit>) {
+    if let Some(unit) = unit {
+        tags.entry("unit".to_string())
+            .or_insert_with(|| unit.as_str().to_string());
+    }
 }
 
 impl<T> DatagramFormat for Option<T>
@@ -43,6 +156,13 @@ where
             Some(t) => t.format(),
         }
     }
+
+    fn validate(&self) -> Result<()> {
+        match &*self {
+            None => Ok(()),
+            Some(t) => t.validate(),
+        }
+    }
 }
 
 // Convert rust HashMap to a -> #<TAG_KEY_1>:<TAG_VALUE_1>,<TAG_2> format.
@@ -69,11 +189,21 @@ where
             }
         }
     }
+
+    fn validate(&self) -> Result<()> {
+        for (k, v) in self.iter() {
+            validate_tag(&k.to_string())?;
+            validate_tag(&v.to_string())?;
+        }
+        Ok(())
+    }
 }
 
 pub struct Config {
     from_addr: String,
     to_addr: String,
+    namespace: Option<String>,
+    constant_tags: HashMap<String, String>,
 }
 
 impl Config {
@@ -95,6 +225,74 @@ impl Config {
             ..self
         }
     }
+
+    /// A prefix applied to every metric name sent by a client built from
+    /// this `Config`, e.g. `namespace("myapp")` turns `requests` into
+    /// `myapp.requests`. Events are left unprefixed: they carry their own
+    /// `_e{...}:` framing rather than a leading name, and prefixing it
+    /// would stop a DogStatsD agent from recognizing the datagram as an
+    /// event at all.
+    pub fn namespace<S: Into<String>>(self, namespace: S) -> Config {
+        Config {
+            namespace: Some(namespace.into()),
+            ..self
+        }
+    }
+
+    /// Tags merged into every metric and event sent by a client built from
+    /// this `Config`, so callers don't have to thread common tags like
+    /// `service:foo,env:prod` through every `send_with_tags` call. Per-call
+    /// tags passed to `send_with_tags` take precedence over these when keys
+    /// collide.
+    pub fn constant_tags(self, constant_tags: HashMap<String, String>) -> Config {
+        Config {
+            constant_tags,
+            ..self
+        }
+    }
+
+    /// Prefix `formatted` with the configured namespace, if any, joined
+    /// with a dot as `namespace.name`; an empty namespace adds no prefix.
+    pub(crate) fn apply_namespace(&self, formatted: String) -> String {
+        match &self.namespace {
+            Some(namespace) if !namespace.is_empty() => format!("{}.{}", namespace, formatted),
+            _ => formatted,
+        }
+    }
+
+    /// Formats `df`, applying the configured namespace unless `df` is an
+    /// event: an event's `_e{...}:` framing must start at byte zero for a
+    /// DogStatsD agent to recognize it, so namespacing it would silently
+    /// corrupt the datagram.
+    pub(crate) fn format_datagram<M: DatagramFormat>(&self, df: &M) -> String {
+        let formatted = df.format();
+        if df.is_event() {
+            formatted
+        } else {
+            self.apply_namespace(formatted)
+        }
+    }
+
+    /// Merge the configured constant tags with per-call tags, letting the
+    /// per-call tags override a constant tag when both set the same key.
+    pub(crate) fn merge_tags(&self, call_tags: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut merged = self.constant_tags.clone();
+        merged.extend(call_tags.iter().map(|(k, v)| (k.clone(), v.clone())));
+        merged
+    }
+
+    /// Checks that `from_addr` is a parseable socket address before a
+    /// client attempts to bind it.
+    pub(crate) fn validate_from_addr(&self) -> Result<()> {
+        self.from_addr.parse::<SocketAddr>().map(|_| ()).map_err(Error::from)
+    }
+
+    /// Checks that every constant tag key/value is wire-safe before a
+    /// client starts sending, so a bad constant tag fails fast at
+    /// construction instead of corrupting every single datagram sent.
+    pub(crate) fn validate_constant_tags(&self) -> Result<()> {
+        self.constant_tags.validate()
+    }
 }
 
 impl default::Default for Config {
@@ -102,6 +300,8 @@ impl default::Default for Config {
         Config {
             from_addr: "127.0.0.1:0".into(),
             to_addr: "127.0.0.1:8125".into(),
+            namespace: None,
+            constant_tags: HashMap::new(),
         }
     }
 }
@@ -110,80 +310,211 @@ impl default::Default for Config {
 pub struct UdpClient {
     socket: UdpSocket,
     config: Config,
+    rng: Pcg32,
 }
 
 impl UdpClient {
     pub async fn new() -> Result<Self> {
-        let config = Config::default();
-        Ok(Self {
-            socket: UdpSocket::bind(config.from_addr.clone()).await?,
-            config,
-        })
+        Self::with_config(Config::default()).await
     }
 
     /// Construct a client with a specific Client.
     pub async fn with_config(config: Config) -> Result<Self> {
+        config.validate_from_addr()?;
+        config.validate_constant_tags()?;
         Ok(Self {
             socket: UdpSocket::bind(config.from_addr.clone()).await?,
             config,
+            rng: Pcg32::seeded(),
         })
     }
 
+    /// Returns `true` if a datagram with the given sample rate should be
+    /// dropped instead of sent.
+    fn should_drop(&self, rate: f64) -> bool {
+        rate < 1.0 && self.rng.next_f64() >= rate
+    }
+
     pub async fn send<M>(&self, df: &M) -> Result<()>
     where
         M: DatagramFormat,
     {
+        df.validate()?;
+        if self.should_drop(df.sample_rate()) {
+            return Ok(());
+        }
+        let mut tags = self.config.constant_tags.clone();
+        insert_unit_tag(&mut tags, df.unit());
+        let mut content = self.config.format_datagram(df);
+        content.push_str(&tags.format());
         self.socket
-            .send_to(df.format().as_bytes(), &self.config.to_addr)
+            .send_to(content.as_bytes(), &self.config.to_addr)
             .await?;
         Ok(())
     }
 
-    pub async fn send_with_tags<M: DatagramFormat>(&self, df: &M, tags: M) -> Result<()> {
-        let content = df.format() + &tags.format();
+    /// Send `df` with `tags` merged on top of the client's constant tags
+    /// (per-call tags win on key collisions).
+    pub async fn send_with_tags<M: DatagramFormat>(
+        &self,
+        df: &M,
+        tags: &HashMap<String, String>,
+    ) -> Result<()> {
+        df.validate()?;
+        tags.validate()?;
+        if self.should_drop(df.sample_rate()) {
+            return Ok(());
+        }
+        let mut merged = self.config.merge_tags(tags);
+        insert_unit_tag(&mut merged, df.unit());
+        let mut content = self.config.format_datagram(df);
+        content.push_str(&merged.format());
         self.socket
             .send_to(content.as_bytes(), &self.config.to_addr)
             .await?;
         Ok(())
     }
+
+    /// Await `fut`, emit how long it took as a `Timer` metric named `name`,
+    /// and return its output, so instrumenting an async block is a one-liner.
+    pub async fn time<F, T>(&self, name: &str, fut: F) -> Result<T>
+    where
+        F: std::future::Future<Output = T>,
+    {
+        let start = std::time::Instant::now();
+        let output = fut.await;
+        self.send(&Metric::Timer::<u32>(name, start.elapsed())).await?;
+        Ok(output)
+    }
 }
 
 /// `UdsClient` sends metrics to DogStatsD server via a local Unix Domain Socket.
 pub struct UdsClient {
     socket: UnixDatagram,
+    config: Config,
+    rng: Pcg32,
 }
 
 impl UdsClient {
-    /// Construct a client with a specific Client.
+    /// Construct a client bound to `path` with a default `Config` (no
+    /// namespace or constant tags).
     pub async fn with_filepath<'a, P>(path: P) -> Result<Self>
     where
         P: Into<borrow::Cow<'a, str>>,
     {
+        Self::with_filepath_and_config(path, Config::default()).await
+    }
+
+    /// Construct a client bound to `path`, applying `config`'s namespace
+    /// and constant tags to every send just like `UdpClient`/
+    /// `BufferedUdpClient`. `Config::from_addr`/`to_addr` are ignored here
+    /// since a Unix socket is addressed by filepath, not a socket address.
+    pub async fn with_filepath_and_config<'a, P>(path: P, config: Config) -> Result<Self>
+    where
+        P: Into<borrow::Cow<'a, str>>,
+    {
+        config.validate_constant_tags()?;
         Ok(Self {
             socket: UnixDatagram::bind(path.into().to_string()).await?,
+            config,
+            rng: Pcg32::seeded(),
         })
     }
 
+    /// Returns `true` if a datagram with the given sample rate should be
+    /// dropped instead of sent.
+    fn should_drop(&self, rate: f64) -> bool {
+        rate < 1.0 && self.rng.next_f64() >= rate
+    }
+
     pub async fn send<M>(&self, df: &M) -> Result<()>
     where
         M: DatagramFormat,
     {
-        self.socket.send(df.format().as_bytes()).await?;
+        df.validate()?;
+        if self.should_drop(df.sample_rate()) {
+            return Ok(());
+        }
+        let mut tags = self.config.constant_tags.clone();
+        insert_unit_tag(&mut tags, df.unit());
+        let mut content = self.config.format_datagram(df);
+        content.push_str(&tags.format());
+        self.socket.send(content.as_bytes()).await?;
         Ok(())
     }
 
-    pub async fn send_with_tags<M: DatagramFormat>(&self, df: &M, tags: M) -> Result<()> {
-        let content = df.format() + &tags.format();
+    /// Send `df` with `tags` merged on top of the client's constant tags
+    /// (per-call tags win on key collisions).
+    pub async fn send_with_tags<M: DatagramFormat>(
+        &self,
+        df: &M,
+        tags: &HashMap<String, String>,
+    ) -> Result<()> {
+        df.validate()?;
+        tags.validate()?;
+        if self.should_drop(df.sample_rate()) {
+            return Ok(());
+        }
+        let mut merged = self.config.merge_tags(tags);
+        insert_unit_tag(&mut merged, df.unit());
+        let mut content = self.config.format_datagram(df);
+        content.push_str(&merged.format());
         self.socket.send(content.as_bytes()).await?;
         Ok(())
     }
+
+    /// Await `fut`, emit how long it took as a `Timer` metric named `name`,
+    /// and return its output, so instrumenting an async block is a one-liner.
+    pub async fn time<F, T>(&self, name: &str, fut: F) -> Result<T>
+    where
+        F: std::future::Future<Output = T>,
+    {
+        let start = std::time::Instant::now();
+        let output = fut.await;
+        self.send(&Metric::Timer::<u32>(name, start.elapsed())).await?;
+        Ok(output)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::DatagramFormat;
+    use super::{Config, DatagramFormat};
+    use crate::{Event, Metric};
     use std::collections::HashMap;
 
+    #[test]
+    fn test_namespace_skips_events() {
+        let config = Config::new().namespace("myapp");
+        let event = Event::new().title("Chungus").text("Big Chungus");
+        assert_eq!(
+            config.format_datagram(&event),
+            "_e{7,11}:Chungus|Big Chungus"
+        );
+    }
+
+    #[test]
+    fn test_namespace_applies_to_metrics() {
+        let config = Config::new().namespace("myapp");
+        let metric = Metric::Inc::<u32>("requests");
+        assert_eq!(config.format_datagram(&metric), "myapp.requests:1|c");
+    }
+
+    #[test]
+    fn test_validate_constant_tags_rejects_reserved_characters() {
+        let mut tags = HashMap::new();
+        tags.insert("env:prod".to_string(), "true".to_string());
+        let config = Config::new().constant_tags(tags);
+        assert!(config.validate_constant_tags().is_err());
+    }
+
+    #[test]
+    fn test_validate_constant_tags_accepts_clean_tags() {
+        let mut tags = HashMap::new();
+        tags.insert("env".to_string(), "prod".to_string());
+        let config = Config::new().constant_tags(tags);
+        assert!(config.validate_constant_tags().is_ok());
+    }
+
     #[test]
     fn test_empty_tag() {
         let timber_resources: HashMap<&str, i32> = [].iter().cloned().collect();
@@ -1,7 +1,8 @@
 use std::fmt;
 use std::io;
+use std::time::Duration;
 
-use crate::DatagramFormat;
+use crate::{validate_metric_name, DatagramFormat, Result};
 use num_integer::Integer;
 
 /// The module, `zoomies::metrics`, implements the following metric types that are accepted by DataDog.
@@ -12,6 +13,7 @@ use num_integer::Integer;
 /// - Set
 /// - Histogram
 /// - Distribution
+/// - Timer
 pub enum Metric<'a, T> {
     /// The Rust representation of a Count Metric in StatsD
     /// The `Count` metric submission type represents the total number of event occurrences in one time interval.
@@ -24,6 +26,9 @@ pub enum Metric<'a, T> {
     Histogram(&'a str, &'a str),
     Distribution(&'a str, &'a str),
     Set(&'a str, &'a str),
+    /// A timing measurement, e.g. how long a request took. Serializes the
+    /// duration as fractional milliseconds.
+    Timer(&'a str, Duration),
 }
 
 impl<'a, T: fmt::Display + Integer> DatagramFormat for Metric<'a, T> {
@@ -33,6 +38,9 @@ impl<'a, T: fmt::Display + Integer> DatagramFormat for Metric<'a, T> {
             Metric::Gauge(metric_name, value) => (metric_name, value.to_string(), "|g"),
             Metric::Histogram(metric_name, value) => (metric_name, value.to_string(), "|h"),
             Metric::Distribution(metric_name, value) => (metric_name, value.to_string(), "|d"),
+            Metric::Timer(metric_name, duration) => {
+                (metric_name, (duration.as_secs_f64() * 1000.0).to_string(), "|ms")
+            }
             count => {
                 let (name, val) = match count {
                     Metric::Inc(metric_name) => (metric_name, "1".to_string()),
@@ -50,6 +58,139 @@ impl<'a, T: fmt::Display + Integer> DatagramFormat for Metric<'a, T> {
         msg.push_str(identifier);
         msg
     }
+
+    fn validate(&self) -> Result<()> {
+        let name = match &*self {
+            Metric::Inc(name) | Metric::Dec(name) => *name,
+            Metric::Arb(name, _) => *name,
+            Metric::Gauge(name, _)
+            | Metric::Histogram(name, _)
+            | Metric::Distribution(name, _)
+            | Metric::Set(name, _)
+            | Metric::Timer(name, _) => *name,
+        };
+        validate_metric_name(name)
+    }
+}
+
+/// Wraps any `DatagramFormat` with a client-side sample rate, matching the
+/// StatsD `metric:value|c|@0.5` convention. The wrapped rate both appends
+/// the `|@<rate>` suffix to the formatted datagram and tells the sending
+/// client to probabilistically drop the datagram instead of transmitting it,
+/// so high-frequency counters and histograms can be throttled without the
+/// agent having to downsample them after the fact.
+pub struct Sampled<M>(pub M, pub f64);
+
+impl<M: DatagramFormat> DatagramFormat for Sampled<M> {
+    fn format(&self) -> String {
+        let Sampled(metric, rate) = self;
+        if *rate >= 1.0 {
+            return metric.format();
+        }
+        let mut msg = metric.format();
+        msg.push_str("|@");
+        msg.push_str(&rate.to_string());
+        msg
+    }
+
+    fn sample_rate(&self) -> f64 {
+        self.1
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.0.validate()
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        self.0.unit()
+    }
+
+    fn is_event(&self) -> bool {
+        self.0.is_event()
+    }
+}
+
+/// A semantic unit for a metric's value (bytes, seconds, count, etc.).
+/// DogStatsD has no native unit field, so a unit is carried as metadata on
+/// the metric and folded into the tag set as `unit:<label>` by the sending
+/// client, rather than changing the formatted datagram itself.
+///
+/// Decimal units (`Kilobytes`, `Megabytes`, `Gigabytes`) scale by 1000 and
+/// binary units (`Kibibytes`, `Mebibytes`, `Gibibytes`) scale by 1024; the
+/// two families are kept distinct so a `Unit::Mebibytes` value is never
+/// conflated with `Unit::Megabytes`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Unit {
+    Count,
+    Bytes,
+    Kilobytes,
+    Megabytes,
+    Gigabytes,
+    Kibibytes,
+    Mebibytes,
+    Gibibytes,
+    Seconds,
+    Milliseconds,
+}
+
+impl Unit {
+    /// The canonical short label used in the `unit:<label>` tag.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Unit::Count => "count",
+            Unit::Bytes => "bytes",
+            Unit::Kilobytes => "kB",
+            Unit::Megabytes => "MB",
+            Unit::Gigabytes => "GB",
+            Unit::Kibibytes => "KiB",
+            Unit::Mebibytes => "MiB",
+            Unit::Gibibytes => "GiB",
+            Unit::Seconds => "s",
+            Unit::Milliseconds => "ms",
+        }
+    }
+
+    /// The numeric factor relative to this unit's base (a count, a byte, or
+    /// a second), so downstream consumers can normalize values. Decimal and
+    /// binary scale families are never mixed.
+    pub fn scale_factor(&self) -> f64 {
+        match self {
+            Unit::Count | Unit::Bytes | Unit::Seconds | Unit::Milliseconds => 1.0,
+            Unit::Kilobytes => 1_000.0,
+            Unit::Megabytes => 1_000_000.0,
+            Unit::Gigabytes => 1_000_000_000.0,
+            Unit::Kibibytes => 1024.0,
+            Unit::Mebibytes => 1024.0 * 1024.0,
+            Unit::Gibibytes => 1024.0 * 1024.0 * 1024.0,
+        }
+    }
+}
+
+/// Wraps any `DatagramFormat` with a `Unit`. The wrapped metric formats and
+/// validates exactly as before; the unit is surfaced via `unit()` so a
+/// sending client can fold `unit:<label>` into the emitted tag set.
+pub struct WithUnit<M>(pub M, pub Unit);
+
+impl<M: DatagramFormat> DatagramFormat for WithUnit<M> {
+    fn format(&self) -> String {
+        self.0.format()
+    }
+
+    fn sample_rate(&self) -> f64 {
+        self.0.sample_rate()
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.0.validate()
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        Some(self.1)
+    }
+
+    fn is_event(&self) -> bool {
+        self.0.is_event()
+    }
 }
 
 /// This trait represents anything that can be turned into a tag.
@@ -128,4 +269,83 @@ mod tests {
             "custom_metric:42|d"
         );
     }
+
+    #[test]
+    fn test_metrics_timer() {
+        assert_eq!(
+            Metric::Timer::<u32>("custom_metric", Duration::from_millis(240)).format(),
+            "custom_metric:240|ms"
+        );
+    }
+
+    #[test]
+    fn test_sampled_appends_rate() {
+        assert_eq!(
+            Sampled(Metric::Inc::<u32>("custom_metric"), 0.5).format(),
+            "custom_metric:1|c|@0.5"
+        );
+    }
+
+    #[test]
+    fn test_sampled_always_send_omits_suffix() {
+        assert_eq!(
+            Sampled(Metric::Inc::<u32>("custom_metric"), 1.0).format(),
+            "custom_metric:1|c"
+        );
+    }
+
+    #[test]
+    fn test_sampled_rate_is_exposed_for_client_dropping() {
+        assert_eq!(Sampled(Metric::Inc::<u32>("custom_metric"), 0.1).sample_rate(), 0.1);
+    }
+
+    #[test]
+    fn test_sampled_forwards_unit_and_is_event() {
+        let sampled_metric = Sampled(
+            Metric::Gauge::<u32>("queue_depth", "42").with_unit(Unit::Count),
+            0.5,
+        );
+        assert_eq!(sampled_metric.unit(), Some(Unit::Count));
+        assert!(!sampled_metric.is_event());
+
+        let sampled_event = Sampled(
+            crate::Event::new().title("Chungus").text("Big Chungus"),
+            0.5,
+        );
+        assert!(sampled_event.is_event());
+    }
+
+    #[test]
+    fn test_metrics_validate_rejects_reserved_characters() {
+        assert!(Metric::Inc::<u32>("custom:metric").validate().is_err());
+        assert!(Metric::Inc::<u32>("custom|metric").validate().is_err());
+        assert!(Metric::Inc::<u32>("custom@metric").validate().is_err());
+        assert!(Metric::Inc::<u32>("custom_metric").validate().is_ok());
+    }
+
+    #[test]
+    fn test_unit_keeps_decimal_and_binary_scales_distinct() {
+        assert_eq!(Unit::Megabytes.as_str(), "MB");
+        assert_eq!(Unit::Mebibytes.as_str(), "MiB");
+        assert_eq!(Unit::Megabytes.scale_factor(), 1_000_000.0);
+        assert_eq!(Unit::Mebibytes.scale_factor(), 1024.0 * 1024.0);
+    }
+
+    #[test]
+    fn test_with_unit_does_not_alter_format_or_sample_rate() {
+        let metric = Metric::Gauge::<u32>("queue_depth", "42").with_unit(Unit::Count);
+        assert_eq!(metric.format(), "queue_depth:42|g");
+        assert_eq!(metric.unit(), Some(Unit::Count));
+    }
+
+    #[test]
+    fn test_with_unit_forwards_is_event() {
+        let event = crate::Event::new()
+            .title("Chungus")
+            .text("Big Chungus")
+            .with_unit(Unit::Count);
+        assert!(event.is_event());
+        let metric = Metric::Gauge::<u32>("queue_depth", "42").with_unit(Unit::Count);
+        assert!(!metric.is_event());
+    }
 }